@@ -0,0 +1,10 @@
+//! Raw native bindings used by the glue. Kept separate from `lib.rs` so that all FFI surface
+//! lives in one place instead of being scattered across the crate.
+
+#![allow(non_camel_case_types)]
+
+use libc;
+
+extern {
+    pub fn eventfd(initval: libc::c_uint, flags: libc::c_int) -> libc::c_int;
+}
@@ -8,31 +8,283 @@
 extern crate compile_msg;
 
 extern crate libc;
+extern crate crossbeam_queue;
 
 use std::ffi::{CString};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::{Sender};
-use std::sync::Mutex;
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread::Thread;
 
+use crossbeam_queue::SegQueue;
+
 #[doc(hidden)]
 pub mod ffi;
 
-/// This static variable  will store the android_app* on creation, and set it back to 0 at
-///  destruction.
-/// Apart from this, the static is never written, so there is no risk of race condition.
-static mut ANDROID_APP: *mut ffi::android_app = 0 as *mut ffi::android_app;
+/// The `ALooper` ident under which `Context::queue_read_fd` is registered, chosen well clear of
+/// the native glue's own `LOOPER_ID_MAIN`/`LOOPER_ID_INPUT`.
+const LOOPER_ID_QUEUE: libc::c_int = 3;
 
 /// This is the structure that serves as user data in the android_app*
 #[doc(hidden)]
 struct Context {
     senders: Mutex<Vec<Sender<Event>>>,
+    sync_handlers: Mutex<Vec<(usize, Sender<(Event, EventSyncGuard)>)>>,
+    next_sync_handler_id: AtomicUsize,
+    multitouch: Mutex<bool>,
+    frame_requested: Mutex<bool>,
+    queue: SegQueue<Event>,
+    queue_write_fd: libc::c_int,
+    queue_read_fd: libc::c_int,
+    window_ready: Mutex<bool>,
+    window_cvar: Condvar,
+}
+
+/// An owned handle to the `android_app*` passed to `android_main`, given to the function named
+/// in `android_start!` instead of it having to reach through a global.
+///
+/// Carries everything that function needs to get at the native window, asset manager and
+/// configuration, and to register itself with the event system.
+pub struct AndroidApp {
+    app: *mut ffi::android_app,
+}
+
+// the android_app* stays valid for the process's lifetime once android_main2 has set it up, so
+// it's fine to hand this handle to the thread running the user's main function
+unsafe impl Send for AndroidApp {}
+
+impl AndroidApp {
+    /// Blocks until the native window has been created, then returns it.
+    ///
+    /// Waits on a condvar signalled by `commands_callback` on `APP_CMD_INIT_WINDOW`, instead of
+    /// spin-locking.
+    pub fn native_window(&self) -> ffi::NativeWindowType {
+        let context = get_context(self.app);
+        let mut ready = context.window_ready.lock().ok().unwrap();
+        while !*ready {
+            ready = context.window_cvar.wait(ready).ok().unwrap();
+        }
+        unsafe { (*self.app).window }
+    }
+
+    /// Returns the asset manager used to load resources bundled with the app.
+    pub fn asset_manager(&self) -> *const ffi::AAssetManager {
+        unsafe {
+            let activity = &*(*self.app).activity;
+            activity.assetManager
+        }
+    }
+
+    /// Returns the current device configuration.
+    pub fn config(&self) -> *mut ffi::AConfiguration {
+        unsafe { (*self.app).config }
+    }
+
+    /// Adds a sender where events will be sent to.
+    pub fn add_sender(&self, sender: Sender<Event>) {
+        get_context(self.app).senders.lock().ok().unwrap().push(sender);
+    }
+
+    /// Registers a handler that participates in the synchronized delivery protocol used for
+    /// destructive events (see `EventSyncGuard`). The handler receives the event alongside a
+    /// guard it must drop once its cleanup for that event is complete.
+    pub fn add_sync_event_handler(&self, sender: Sender<(Event, EventSyncGuard)>)
+        -> SyncEventHandlerId
+    {
+        let context = get_context(self.app);
+        let id = context.next_sync_handler_id.fetch_add(1, Ordering::SeqCst);
+        context.sync_handlers.lock().ok().unwrap().push((id, sender));
+        SyncEventHandlerId(id)
+    }
+
+    /// Unregisters a handler previously passed to `add_sync_event_handler`.
+    pub fn remove_sync_event_handler(&self, id: SyncEventHandlerId) {
+        let SyncEventHandlerId(id) = id;
+        get_context(self.app).sync_handlers.lock().ok().unwrap()
+            .retain(|&(handler_id, _)| handler_id != id);
+    }
+
+    /// Enables or disables reporting one `Motion` per active pointer.
+    ///
+    /// When disabled (the default), only the first pointer is reported.
+    pub fn set_multitouch(&self, enabled: bool) {
+        *get_context(self.app).multitouch.lock().ok().unwrap() = enabled;
+    }
+
+    /// Wakes up the poll loop from any thread, delivering `Event::Wake` to every registered
+    /// sender.
+    pub fn wake_event_loop(&self) {
+        push_queue_entry(self.app, Event::Wake);
+    }
+
+    /// Requests that a single `Event::Frame` be delivered on the next display vsync.
+    ///
+    /// Safe to call from any thread: it only sets a flag and wakes the poll loop, which is the
+    /// one thread allowed to talk to `AChoreographer`. Repeated calls before the poll loop next
+    /// checks the flag are coalesced into a single frame callback; a call made after that
+    /// callback has been posted schedules another one.
+    pub fn request_frame(&self) {
+        *get_context(self.app).frame_requested.lock().ok().unwrap() = true;
+        self.wake_event_loop();
+    }
+
+    /// Opens a bundled asset for streaming or random-access reads.
+    ///
+    /// Unlike `load_asset`, this does not read the whole asset into memory upfront, so it also
+    /// works for compressed assets (for which the underlying buffer isn't directly addressable).
+    pub fn open_asset(&self, filename: &str) -> Result<Asset, AssetError> {
+        let filename_c_str = CString::from_slice(filename.as_bytes());
+        let asset = unsafe {
+            ffi::AAssetManager_open(self.asset_manager(),
+                filename_c_str.as_slice_with_nul().as_ptr(), ffi::MODE_STREAMING)
+        };
+        if asset.is_null() {
+            return Err(AssetError::AssetMissing);
+        }
+        Ok(Asset { asset: asset })
+    }
+
+    /// Reads an asset bundled with the app in one go.
+    ///
+    /// A convenience built on top of `open_asset` for callers who just want the whole thing;
+    /// use `open_asset` directly to stream a large asset incrementally instead.
+    pub fn load_asset(&self, filename: &str) -> Result<Vec<u8>, AssetError> {
+        let mut asset = try!(self.open_asset(filename));
+        asset.read_to_end().map_err(|_| AssetError::EmptyBuffer)
+    }
+}
+
+/// A bundled asset opened with `AndroidApp::open_asset`, for streaming or random-access reads.
+///
+/// The underlying native asset is closed when this is dropped.
+pub struct Asset {
+    asset: *const ffi::Asset,
+}
+
+unsafe impl Send for Asset {}
+
+impl Drop for Asset {
+    fn drop(&mut self) {
+        unsafe { ffi::AAsset_close(self.asset) };
+    }
 }
 
+impl Reader for Asset {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::IoResult<usize> {
+        let result = unsafe {
+            ffi::AAsset_read(self.asset, buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len() as libc::size_t)
+        };
+        if result < 0 {
+            return Err(std::io::standard_error(std::io::OtherIoError));
+        }
+        if result == 0 {
+            return Err(std::io::standard_error(std::io::EndOfFile));
+        }
+        Ok(result as usize)
+    }
+}
+
+impl Seek for Asset {
+    fn tell(&self) -> std::io::IoResult<u64> {
+        let length = unsafe { ffi::AAsset_getLength(self.asset) };
+        let remaining = unsafe { ffi::AAsset_getRemainingLength(self.asset) };
+        Ok((length - remaining) as u64)
+    }
+
+    fn seek(&mut self, pos: i64, style: std::io::SeekStyle) -> std::io::IoResult<()> {
+        let whence = match style {
+            std::io::SeekSet => 0,
+            std::io::SeekCur => 1,
+            std::io::SeekEnd => 2,
+        };
+        let result = unsafe { ffi::AAsset_seek(self.asset, pos as libc::off_t, whence) };
+        if result < 0 {
+            return Err(std::io::standard_error(std::io::OtherIoError));
+        }
+        Ok(())
+    }
+}
+
+
+/// Proof that a subscriber has finished handling a synchronized, destructive event.
+///
+/// Android requires cleanup (freeing a surface, releasing an input queue, ...) to complete
+/// before the native callback that announced the destruction returns. Holding one of these
+/// keeps `commands_callback` blocked; dropping it (or letting it go out of scope) signals that
+/// this subscriber is done.
+pub struct EventSyncGuard {
+    remaining: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl Drop for EventSyncGuard {
+    fn drop(&mut self) {
+        let &(ref lock, ref cvar) = &*self.remaining;
+        let mut remaining = lock.lock().ok().unwrap();
+        *remaining -= 1;
+        if *remaining == 0 {
+            cvar.notify_all();
+        }
+    }
+}
+
+/// A single pointer sample taken from a motion event.
+pub struct Motion {
+    pub action: MotionAction,
+    pub pointer_id: i32,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Copy for Motion {}
+
+/// What a pointer was doing at the time a `Motion` was recorded.
+pub enum MotionAction {
+    Down,
+    Up,
+    Move,
+}
+
+impl Copy for MotionAction {}
+
 /// An event triggered by the Android environment.
 pub enum Event {
-    EventUp,
-    EventDown,
-    EventMove(i32, i32),
+    Motion(Motion),
+    KeyDown(i32),
+    KeyUp(i32),
+
+    /// The window used to draw has been created. A renderer should (re)build its surface here.
+    InitWindow,
+    /// The window used to draw is about to be destroyed.
+    TermWindow,
+    /// The app's window gained input focus.
+    GainedFocus,
+    /// The app's window lost input focus.
+    LostFocus,
+    /// The system asked the app to save its state, in case it gets killed.
+    SaveState,
+    /// The activity has been paused.
+    Pause,
+    /// The activity has been resumed.
+    Resume,
+    /// The activity is being stopped.
+    Stop,
+    /// The activity is being destroyed, this is the last event it will ever receive.
+    Destroy,
+    /// The window has been resized.
+    WindowResized,
+    /// The window needs to be redrawn.
+    WindowRedrawNeeded,
+    /// The device configuration changed.
+    ConfigChanged,
+    /// The system is low on memory.
+    LowMemory,
+
+    /// The poll loop was woken up by a call to `AndroidApp::wake_event_loop`.
+    Wake,
+
+    /// An `AChoreographer` frame callback fired, carrying its timestamp in nanoseconds.
+    Frame(i64),
 }
 
 impl Copy for Event {}
@@ -58,7 +310,7 @@ macro_rules! android_start(
             #[inline(never)]
             #[allow(non_snake_case)]
             pub extern "C" fn android_main(app: *mut ()) {
-                android_glue::android_main2(app, move|| ::$main());
+                android_glue::android_main2(app, move|android_app| ::$main(android_app));
             }
         }
     )
@@ -67,26 +319,52 @@ macro_rules! android_start(
 /// This is the function that must be called by `android_main`
 #[doc(hidden)]
 pub fn android_main2<F>(app: *mut (), main_function: F)
-    where F: FnOnce(), F: Send
+    where F: FnOnce(AndroidApp), F: Send
 {
     use std::{mem, ptr};
 
     write_log("Entering android_main");
 
-    unsafe { ANDROID_APP = std::mem::transmute(app) };
-    let app: &mut ffi::android_app = unsafe { std::mem::transmute(app) };
+    let app_ptr: *mut ffi::android_app = unsafe { std::mem::transmute(app) };
+    let app: &mut ffi::android_app = unsafe { &mut *app_ptr };
+
+    // the fd that Context::queue's producers write to in order to wake the poll loop below
+    let queue_fd = unsafe { ffi::eventfd(0, 0) };
+    if queue_fd < 0 {
+        panic!("eventfd creation failed");
+    }
 
     // creating the context that will be passed to the callback
-    let context = Context { senders: Mutex::new(Vec::new()) };
+    let context = Context {
+        senders: Mutex::new(Vec::new()),
+        sync_handlers: Mutex::new(Vec::new()),
+        next_sync_handler_id: AtomicUsize::new(0),
+        multitouch: Mutex::new(false),
+        frame_requested: Mutex::new(false),
+        queue: SegQueue::new(),
+        queue_write_fd: queue_fd,
+        queue_read_fd: queue_fd,
+        window_ready: Mutex::new(false),
+        window_cvar: Condvar::new(),
+    };
     app.onAppCmd = commands_callback;
     app.onInputEvent = inputs_callback;
     app.userData = unsafe { std::mem::transmute(&context) };
 
-    // executing the main function in parallel
+    // registering our fd with the looper, so that pushing to the queue wakes up `pollAll` below
+    unsafe {
+        let looper = ffi::ALooper_forThread();
+        ffi::ALooper_addFd(looper, context.queue_read_fd, LOOPER_ID_QUEUE,
+            ffi::ALOOPER_EVENT_INPUT, ptr::null_mut(), ptr::null_mut());
+    }
+
+    // executing the main function in parallel, handing it an owned handle instead of letting it
+    // reach through a global
+    let android_app = AndroidApp { app: app_ptr };
     let g = Thread::spawn(move|| {
         std::io::stdio::set_stdout(box std::io::LineBufferedWriter::new(ToLogWriter));
         std::io::stdio::set_stderr(box std::io::LineBufferedWriter::new(ToLogWriter));
-        main_function()
+        main_function(android_app)
     });
 
     // polling for events forever
@@ -101,16 +379,19 @@ pub fn android_main2<F>(app: *mut (), main_function: F)
             let ident = ffi::ALooper_pollAll(-1, ptr::null_mut(), &mut events,
                 &mut source);
 
-            // processing the event
-            if !source.is_null() {
+            if ident == LOOPER_ID_QUEUE {
+                drain_queue(app_ptr);
+            } else if !source.is_null() {
+                // processing the event
                 let source: *mut ffi::android_poll_source = mem::transmute(source);
-                ((*source).process)(ANDROID_APP, source);
+                ((*source).process)(app_ptr, source);
             }
+
+            // a redraw may have been requested while we were blocked in pollAll, or while
+            // handling whatever woke us up above; schedule it now, coalesced into one callback
+            maybe_schedule_frame(app_ptr);
         }
     }
-
-    // terminating the application
-    unsafe { ANDROID_APP = 0 as *mut ffi::android_app };
 }
 
 /// Writer that will redirect what is written to it to the logs.
@@ -127,40 +408,83 @@ impl Writer for ToLogWriter {
 }
 
 /// The callback for inputs.
-pub extern fn inputs_callback(_: *mut ffi::android_app, event: *const ffi::AInputEvent)
+pub extern fn inputs_callback(app: *mut ffi::android_app, event: *const ffi::AInputEvent)
     -> libc::int32_t
 {
-    fn send_event(event: Event) {
-        let senders = get_context().senders.lock().ok().unwrap();
-        for sender in senders.iter() {
-            sender.send(event);
-        }
-    }
-    fn get_xy(event: *const ffi::AInputEvent) -> (i32, i32) {
-        let x = unsafe { ffi::AMotionEvent_getX(event, 0) };
-        let y = unsafe { ffi::AMotionEvent_getY(event, 0) };
+    fn get_xy(event: *const ffi::AInputEvent, pointer_index: i32) -> (i32, i32) {
+        let x = unsafe { ffi::AMotionEvent_getX(event, pointer_index) };
+        let y = unsafe { ffi::AMotionEvent_getY(event, pointer_index) };
         (x as i32, y as i32)
     }
+    fn send_motion(app: *mut ffi::android_app, event: *const ffi::AInputEvent,
+        action: MotionAction, pointer_index: i32)
+    {
+        let pointer_id = unsafe { ffi::AMotionEvent_getPointerId(event, pointer_index) };
+        let (x, y) = get_xy(event, pointer_index);
+        send_event(app, Event::Motion(Motion {
+            action: action, pointer_id: pointer_id, x: x, y: y
+        }));
+    }
+
+    let event_type = unsafe { ffi::AInputEvent_getType(event) };
+
+    if event_type == ffi::AINPUT_EVENT_TYPE_KEY {
+        let action = unsafe { ffi::AKeyEvent_getAction(event) };
+        let keycode = unsafe { ffi::AKeyEvent_getKeyCode(event) };
+        match action {
+            ffi::AKEY_EVENT_ACTION_DOWN => send_event(app, Event::KeyDown(keycode)),
+            ffi::AKEY_EVENT_ACTION_UP => send_event(app, Event::KeyUp(keycode)),
+            _ => (),
+        }
+        return 0;
+    }
+
     let action = unsafe { ffi::AMotionEvent_getAction(event) };
     let action_code = action & ffi::AMOTION_EVENT_ACTION_MASK;
+
+    let multitouch = *get_context(app).multitouch.lock().ok().unwrap();
+    if multitouch {
+        // Only the pointer named by the action's index bits actually changed state; every
+        // other pointer reported alongside it is still mid-gesture and must be read as a move,
+        // not re-sent as a fresh down/up.
+        let changed_pointer_index = (action & ffi::AMOTION_EVENT_ACTION_POINTER_INDEX_MASK)
+            >> ffi::AMOTION_EVENT_ACTION_POINTER_INDEX_SHIFT;
+        let changed_action = match action_code {
+            ffi::AMOTION_EVENT_ACTION_UP
+                | ffi::AMOTION_EVENT_ACTION_OUTSIDE
+                | ffi::AMOTION_EVENT_ACTION_CANCEL
+                | ffi::AMOTION_EVENT_ACTION_POINTER_UP => MotionAction::Up,
+            ffi::AMOTION_EVENT_ACTION_DOWN
+                | ffi::AMOTION_EVENT_ACTION_POINTER_DOWN => MotionAction::Down,
+            _ => MotionAction::Move,
+        };
+        let pointer_count = unsafe { ffi::AMotionEvent_getPointerCount(event) };
+        for pointer_index in range(0, pointer_count) {
+            let action = if pointer_index == changed_pointer_index {
+                changed_action
+            } else {
+                MotionAction::Move
+            };
+            send_motion(app, event, action, pointer_index);
+        }
+        return 0;
+    }
+
     match action_code {
         ffi::AMOTION_EVENT_ACTION_UP
             | ffi::AMOTION_EVENT_ACTION_OUTSIDE
             | ffi::AMOTION_EVENT_ACTION_CANCEL
             | ffi::AMOTION_EVENT_ACTION_POINTER_UP =>
         {
-            send_event(Event::EventUp);
+            send_motion(app, event, MotionAction::Up, 0);
         },
         ffi::AMOTION_EVENT_ACTION_DOWN
             | ffi::AMOTION_EVENT_ACTION_POINTER_DOWN =>
         {
-            let (x, y) = get_xy(event);
-            send_event(Event::EventMove(x, y));
-            send_event(Event::EventDown);
+            send_motion(app, event, MotionAction::Down, 0);
         },
         _ => {
-            let (x, y) = get_xy(event);
-            send_event(Event::EventMove(x, y));
+            send_motion(app, event, MotionAction::Move, 0);
         },
     }
     0
@@ -168,63 +492,181 @@ pub extern fn inputs_callback(_: *mut ffi::android_app, event: *const ffi::AInpu
 
 /// The callback for commands.
 #[doc(hidden)]
-pub extern fn commands_callback(_: *mut ffi::android_app, command: libc::int32_t) {
-    let context = get_context();
-
+pub extern fn commands_callback(app: *mut ffi::android_app, command: libc::int32_t) {
     match command {
         ffi::APP_CMD_INIT_WINDOW => {
+            let context = get_context(app);
+            *context.window_ready.lock().ok().unwrap() = true;
+            context.window_cvar.notify_all();
 
+            send_event(app, Event::InitWindow);
         },
 
         ffi::APP_CMD_SAVE_STATE => {
-
+            send_event(app, Event::SaveState);
         },
 
         ffi::APP_CMD_TERM_WINDOW => {
+            let context = get_context(app);
+            *context.window_ready.lock().ok().unwrap() = false;
+            context.window_cvar.notify_all();
 
+            // the window is about to be destroyed: block here until every sync handler has
+            // finished tearing down whatever it built on top of it
+            send_sync_event(app, Event::TermWindow);
         },
 
         ffi::APP_CMD_GAINED_FOCUS => {
-
+            send_event(app, Event::GainedFocus);
         },
 
         ffi::APP_CMD_LOST_FOCUS => {
+            send_event(app, Event::LostFocus);
+        },
+
+        ffi::APP_CMD_PAUSE => {
+            send_event(app, Event::Pause);
+        },
 
+        ffi::APP_CMD_RESUME => {
+            send_event(app, Event::Resume);
+        },
+
+        ffi::APP_CMD_STOP => {
+            send_event(app, Event::Stop);
+        },
+
+        ffi::APP_CMD_DESTROY => {
+            send_event(app, Event::Destroy);
+        },
+
+        ffi::APP_CMD_WINDOW_RESIZED => {
+            send_event(app, Event::WindowResized);
+        },
+
+        ffi::APP_CMD_WINDOW_REDRAW_NEEDED => {
+            send_event(app, Event::WindowRedrawNeeded);
+        },
+
+        ffi::APP_CMD_CONFIG_CHANGED => {
+            send_event(app, Event::ConfigChanged);
+        },
+
+        ffi::APP_CMD_LOW_MEMORY => {
+            send_event(app, Event::LowMemory);
         },
 
         _ => ()
     }
 }
 
-/// Returns the current Context.
-fn get_context() -> &'static Context {
-    let context = unsafe { (*ANDROID_APP).userData };
+/// Returns the Context belonging to this android_app.
+fn get_context(app: *mut ffi::android_app) -> &'static Context {
+    let context = unsafe { (*app).userData };
     unsafe { std::mem::transmute(context) }
 }
 
-/// Adds a sender where events will be sent to.
-pub fn add_sender(sender: Sender<Event>) {
-    get_context().senders.lock().ok().unwrap().push(sender);
+/// Sends an event to every sender registered with `AndroidApp::add_sender`.
+fn send_event(app: *mut ffi::android_app, event: Event) {
+    let senders = get_context(app).senders.lock().ok().unwrap();
+    for sender in senders.iter() {
+        sender.send(event);
+    }
 }
 
-/// Returns a handle to the native window.
-pub unsafe fn get_native_window() -> ffi::NativeWindowType {
-    if ANDROID_APP.is_null() {
-        panic!("The application was not initialized from android_main");
+/// Pushes an event onto `Context::queue` and wakes the poll loop so it gets drained.
+fn push_queue_entry(app: *mut ffi::android_app, event: Event) {
+    let context = get_context(app);
+    context.queue.push(event);
+    let one: u64 = 1;
+    unsafe {
+        libc::write(context.queue_write_fd, &one as *const u64 as *const libc::c_void, 8);
     }
+}
 
-    loop {
-        let value = (*ANDROID_APP).window;
-        if !value.is_null() {
-            return value;
-        }
+/// Drains `Context::queue`, dispatching every entry to the plain `add_sender` subscribers.
+/// Called from the poll loop, on the looper thread, whenever `LOOPER_ID_QUEUE` becomes readable.
+fn drain_queue(app: *mut ffi::android_app) {
+    let context = get_context(app);
 
-        // spin-locking
-        std::io::timer::sleep(std::time::Duration::milliseconds(10));
+    // acknowledge the wakeup(s) accumulated on the eventfd
+    let mut discard: u64 = 0;
+    unsafe {
+        libc::read(context.queue_read_fd, &mut discard as *mut u64 as *mut libc::c_void, 8);
+    }
+
+    while let Some(event) = context.queue.pop() {
+        send_event(app, event);
+    }
+}
+
+/// If a frame has been requested since the last check, posts a single `AChoreographer` frame
+/// callback and clears the request. Must run on the looper thread, since that's the only
+/// thread `AChoreographer_getInstance` is valid on.
+fn maybe_schedule_frame(app: *mut ffi::android_app) {
+    let context = get_context(app);
+    let mut requested = context.frame_requested.lock().ok().unwrap();
+    if !*requested {
+        return;
+    }
+    *requested = false;
+    drop(requested);
+
+    unsafe {
+        let choreographer = ffi::AChoreographer_getInstance();
+        ffi::AChoreographer_postFrameCallback(choreographer, frame_callback,
+            app as *mut libc::c_void);
+    }
+}
+
+/// The `AChoreographer` frame callback: forwards the vsync timestamp as `Event::Frame`.
+extern fn frame_callback(frame_time_nanos: libc::int64_t, data: *mut libc::c_void) {
+    let app = data as *mut ffi::android_app;
+    send_event(app, Event::Frame(frame_time_nanos as i64));
+}
+
+/// Delivers a destructive event to every sync handler and blocks until all of them have
+/// finished handling it (i.e. dropped their `EventSyncGuard`).
+///
+/// The plain `add_sender` subscribers always receive the event too, whether or not any sync
+/// handler is registered; they are not waited for.
+///
+/// This always runs on the looper thread, synchronously inside `commands_callback`: that same
+/// thread is also the only one that drains `Context::queue` (from its own `pollAll` loop), which
+/// can't turn around again until `commands_callback` returns. So unlike the async events, this
+/// delivers directly to the handlers here rather than going through the queue, which would
+/// otherwise deadlock waiting for a drain that can never happen.
+fn send_sync_event(app: *mut ffi::android_app, event: Event) {
+    let context = get_context(app);
+
+    // The plain `add_sender` subscribers are fire-and-forget and never block teardown, so they
+    // still get the event even though sync handlers are also being notified below.
+    send_event(app, event);
+
+    let handlers = context.sync_handlers.lock().ok().unwrap();
+    if handlers.is_empty() {
+        return;
+    }
+
+    let remaining = Arc::new((Mutex::new(handlers.len()), Condvar::new()));
+    for &(_, ref sender) in handlers.iter() {
+        let guard = EventSyncGuard { remaining: remaining.clone() };
+        sender.send((event, guard));
+    }
+    drop(handlers);
+
+    let &(ref lock, ref cvar) = &*remaining;
+    let mut count = lock.lock().ok().unwrap();
+    while *count > 0 {
+        count = cvar.wait(count).ok().unwrap();
     }
 }
 
-/// 
+/// Identifies a handler registered with `AndroidApp::add_sync_event_handler`, for use with
+/// `AndroidApp::remove_sync_event_handler`.
+pub struct SyncEventHandlerId(usize);
+
+///
 pub fn write_log(message: &str) {
     let message = message.as_bytes();
     let message = CString::from_slice(message).as_slice_with_nul().as_ptr();
@@ -234,50 +676,8 @@ pub fn write_log(message: &str) {
 }
 
 pub enum AssetError {
+    /// No asset exists under that filename.
     AssetMissing,
+    /// The asset could not be read.
     EmptyBuffer,
 }
-
-pub fn load_asset(filename: &str) -> Result<Vec<u8>, AssetError> {
-    struct AssetCloser {
-        asset: *const ffi::Asset,
-    }
-
-    impl Drop for AssetCloser {
-        fn drop(&mut self) {
-            unsafe {
-                ffi::AAsset_close(self.asset)
-            };
-        }
-    }
-
-    unsafe fn get_asset_manager() -> *const ffi::AAssetManager {
-        let app = &*ANDROID_APP;
-        let activity = &*app.activity;
-        activity.assetManager
-    }
-
-    let filename_c_str = CString::from_slice(filename.as_bytes())
-        .as_slice_with_nul().as_ptr();
-    let asset = unsafe {
-        ffi::AAssetManager_open(
-            get_asset_manager(), filename_c_str, ffi::MODE_STREAMING)
-    };
-    if asset.is_null() {
-        return Err(AssetError::AssetMissing);
-    }
-    let _asset_closer = AssetCloser{asset: asset};
-    let len = unsafe {
-        ffi::AAsset_getLength(asset)
-    };
-    let buff = unsafe {
-        ffi::AAsset_getBuffer(asset)
-    };
-    if buff.is_null() {
-        return Err(AssetError::EmptyBuffer);
-    }
-    let vec = unsafe {
-        Vec::from_raw_buf(buff as *const u8, len as usize)
-    };
-    Ok(vec)
-}